@@ -1,9 +1,17 @@
 use nannou::{color::named, prelude::*, state::mouse::ButtonMap};
 use rand::Rng;
+use std::f32::consts::TAU;
+use std::path::Path;
 use std::string::ToString;
 
+mod wasm_host;
+
 fn main() {
-    nannou::app(model).update(update).simple_window(view).run();
+    nannou::app(model)
+        .update(update)
+        .event(event)
+        .simple_window(view)
+        .run();
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -19,6 +27,10 @@ impl ToString for Color {
     }
 }
 
+impl Color {
+    const ALL: [Color; 3] = [Color::Honeydew, Color::SteelBlue, Color::Black];
+}
+
 type Rgb = Srgb<u8>;
 
 impl From<Color> for Rgb {
@@ -44,9 +56,118 @@ impl Point {
     }
 }
 
-trait Nannou {
-    fn display(&self, draw: &Draw);
-    fn update(&mut self);
+/// The result of testing two circles for intersection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Contact {
+    None,
+    Tangent(Point),
+    Secant(Point, Point),
+}
+
+/// Exact circle-circle intersection: two circles touch when
+/// `|r1 - r2| <= d <= r1 + r2`, where `d` is the distance between origins.
+/// Circles with coincident origins (`d == 0`) are reported as `Contact::None`.
+fn circle_circle_contact(origin1: Point, r1: f32, origin2: Point, r2: f32) -> Contact {
+    let d = origin1.distance_to(&origin2);
+    if d == 0.0 || d > r1 + r2 || d < (r1 - r2).abs() {
+        return Contact::None;
+    }
+
+    let a = (d * d + r1 * r1 - r2 * r2) / (2.0 * d);
+    let mid = Point::new(
+        origin1.x + a * (origin2.x - origin1.x) / d,
+        origin1.y + a * (origin2.y - origin1.y) / d,
+    );
+
+    let h_sq = r1 * r1 - a * a;
+    if h_sq <= 0.0 {
+        return Contact::Tangent(mid);
+    }
+
+    let h = h_sq.sqrt();
+    let perp_x = -(origin2.y - origin1.y) / d;
+    let perp_y = (origin2.x - origin1.x) / d;
+    Contact::Secant(
+        Point::new(mid.x + h * perp_x, mid.y + h * perp_y),
+        Point::new(mid.x - h * perp_x, mid.y - h * perp_y),
+    )
+}
+
+/// Of the two arcs between angles `a1` and `a2` on a circle centered at
+/// `center`, returns the `(start_angle, span)` of whichever one lies outside
+/// the other circle (`other`/`other_radius`).
+fn outside_arc(center: Point, radius: f32, other: Point, other_radius: f32, a1: f32, a2: f32) -> (f32, f32) {
+    let span = normalize_angle(a2 - a1);
+    let mid = a1 + span / 2.0;
+    let mid_point = Point::new(center.x + radius * mid.cos(), center.y + radius * mid.sin());
+
+    if mid_point.distance_to(&other) > other_radius {
+        (a1, span)
+    } else {
+        (a2, TAU - span)
+    }
+}
+
+fn normalize_angle(mut angle: f32) -> f32 {
+    while angle < 0.0 {
+        angle += TAU;
+    }
+    while angle >= TAU {
+        angle -= TAU;
+    }
+    angle
+}
+
+/// Draws the arc of a circle from `start_angle`, sweeping counter-clockwise
+/// by `span` radians, as a polyline.
+fn draw_arc(draw: &Draw, center: Point, radius: f32, start_angle: f32, span: f32, color: Rgb, weight: f32) {
+    const SEGMENTS: usize = 48;
+    let points = (0..=SEGMENTS).map(|i| {
+        let t = start_angle + span * (i as f32 / SEGMENTS as f32);
+        pt2(center.x + radius * t.cos(), center.y + radius * t.sin())
+    });
+    draw.polyline().weight(weight).color(color).points(points);
+}
+
+/// Advances a collection of rings by one frame using a pluggable `RingBehavior`.
+trait Simulate {
+    fn update(&mut self, behavior: &mut dyn RingBehavior);
+}
+
+/// Renders a collection of rings, optionally as interleaved arcs where they overlap.
+trait Render {
+    fn display(&self, draw: &Draw, arc_mode: bool);
+}
+
+/// User-facing actions triggered from the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Clear,
+    Pause,
+    Step,
+    UndoLastRing,
+    CycleBackground,
+    SpeedUp,
+    SlowDown,
+    ToggleDla,
+    ToggleArcDisplay,
+}
+
+impl Command {
+    fn from_key(key: Key) -> Option<Self> {
+        match key {
+            Key::C => Some(Command::Clear),
+            Key::P => Some(Command::Pause),
+            Key::Right => Some(Command::Step),
+            Key::U => Some(Command::UndoLastRing),
+            Key::B => Some(Command::CycleBackground),
+            Key::Up => Some(Command::SpeedUp),
+            Key::Down => Some(Command::SlowDown),
+            Key::D => Some(Command::ToggleDla),
+            Key::A => Some(Command::ToggleArcDisplay),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -55,6 +176,59 @@ enum RingDirection {
     Shrinking,
 }
 
+impl RingDirection {
+    fn to_i32(self) -> i32 {
+        match self {
+            RingDirection::Growing => 0,
+            RingDirection::Shrinking => 1,
+        }
+    }
+
+    fn from_i32(value: i32) -> Self {
+        match value {
+            1 => RingDirection::Shrinking,
+            _ => RingDirection::Growing,
+        }
+    }
+}
+
+/// A pluggable per-frame simulation rule for a single ring.
+///
+/// Implementations receive a ring's current state and whether it's
+/// intersecting another ring this frame, and return its new ABSOLUTE radius
+/// (not a delta) and direction (encoded per `RingDirection::{to,from}_i32`).
+/// `scripts/default_behavior.wat` is a template replicating `DefaultBehavior`
+/// for the WASM-backed implementation in `wasm_host`.
+trait RingBehavior {
+    fn update_ring(&mut self, radius: f32, growth_rate: f32, direction: i32, intersecting: bool) -> (f32, i32);
+}
+
+/// Replicates the original fixed grow/shrink-on-intersect rule.
+struct DefaultBehavior;
+
+impl RingBehavior for DefaultBehavior {
+    fn update_ring(&mut self, radius: f32, growth_rate: f32, direction: i32, intersecting: bool) -> (f32, i32) {
+        let mut direction = RingDirection::from_i32(direction);
+        if intersecting {
+            direction = match direction {
+                RingDirection::Growing => RingDirection::Shrinking,
+                RingDirection::Shrinking => RingDirection::Growing,
+            };
+        }
+
+        let mut radius = match direction {
+            RingDirection::Growing => radius + growth_rate,
+            RingDirection::Shrinking => radius - growth_rate,
+        };
+
+        if radius < 0.0 && direction == RingDirection::Shrinking {
+            direction = RingDirection::Growing;
+        }
+
+        (radius, direction.to_i32())
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Ring {
     color: Rgb,
@@ -70,30 +244,28 @@ impl Ring {
         Self::default()
     }
 
-    fn is_intersecting(&self, other: &Self) -> bool {
+    fn contact_with(&self, other: &Self) -> Contact {
         if self == other {
-            return false;
+            return Contact::None;
         }
         match (self.direction, other.direction) {
-            (RingDirection::Shrinking, RingDirection::Shrinking) => return false,
+            (RingDirection::Shrinking, RingDirection::Shrinking) => return Contact::None,
             _ => (),
         }
-        let distance = self.origin.distance_to(&other.origin);
-
-        let r1 = self.radius;
-        let r2 = other.radius;
-
-        let external_range = r1 + r2 - self.growth_rate..r1 + r2 + self.growth_rate;
-        let internal_range = (r1 - r2).abs() - self.growth_rate..(r1 - r2).abs() + self.growth_rate;
+        circle_circle_contact(self.origin, self.radius, other.origin, other.radius)
+    }
 
-        let intersecting = distance > external_range.start && distance < external_range.end || distance > internal_range.start && distance < internal_range.end;
-        if intersecting == true {
-            println!("[{:?}, rad={:?}, direction={:?}] and [{:?}, rad={:?}, direction={:?}] intersect!", self.origin, self.radius, self.direction, other.origin, other.radius, other.direction);
-        }
-        intersecting
+    fn is_intersecting(&self, other: &Self) -> bool {
+        self.contact_with(other) != Contact::None
     }
 
-    fn display(&self, draw: &Draw) {
+    fn display(&self, draw: &Draw, rings: &[Ring], arc_mode: bool) {
+        if arc_mode {
+            if let Some((start, span)) = self.visible_arc(rings) {
+                draw_arc(draw, self.origin, self.radius, start, span, Rgb::from(self.color), self.weight);
+                return;
+            }
+        }
         draw.ellipse()
             .no_fill()
             .w(self.radius*2.0)
@@ -103,30 +275,20 @@ impl Ring {
             .stroke_weight(self.weight);
     }
 
-    fn update(&mut self, rings: &Vec<Ring>) {
-        let mut intersecting = false;
-        for other in rings {
-            if (self.is_intersecting(other)) {
-                intersecting = true;
-                break;
+    /// Finds this ring's first secant overlap among `rings` and returns the
+    /// `(start_angle, span)` of the arc lying outside the other ring, so
+    /// secant rings render as interleaved lens shapes rather than two full
+    /// overlapping circles.
+    fn visible_arc(&self, rings: &[Ring]) -> Option<(f32, f32)> {
+        rings.iter().find_map(|other| {
+            if let Contact::Secant(p1, p2) = self.contact_with(other) {
+                let a1 = (p1.y - self.origin.y).atan2(p1.x - self.origin.x);
+                let a2 = (p2.y - self.origin.y).atan2(p2.x - self.origin.x);
+                Some(outside_arc(self.origin, self.radius, other.origin, other.radius, a1, a2))
+            } else {
+                None
             }
-        }
-        if intersecting {
-            self.direction = match self.direction {
-                RingDirection::Growing => RingDirection::Shrinking,
-                RingDirection::Shrinking => RingDirection::Growing,
-            };
-        }
-
-        match self.direction {
-            RingDirection::Growing => self.radius += self.growth_rate,
-            RingDirection::Shrinking => self.radius -= self.growth_rate,
-        }
-
-        match (self.radius < 0.0, self.direction) {
-            (true, RingDirection::Shrinking) => self.direction = RingDirection::Growing,
-            _ => (),
-        }
+        })
     }
 
     fn set_origin(&mut self, x: f32, y: f32) {
@@ -135,6 +297,45 @@ impl Ring {
 
 }
 
+/// Serializes a collection of rings to a standalone SVG document.
+trait SvgExport {
+    fn save_svg(&self, path: &Path, win_rect: Rect, bg_color: Rgb);
+}
+
+impl SvgExport for Vec<Ring> {
+    fn save_svg(&self, path: &Path, win_rect: Rect, bg_color: Rgb) {
+        let w = win_rect.w();
+        let h = win_rect.h();
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n"
+        );
+        svg.push_str(&format!(
+            "  <rect width=\"{w}\" height=\"{h}\" fill=\"{}\"/>\n",
+            rgb_to_hex(&bg_color)
+        ));
+
+        // nannou is centered and y-up; SVG is top-left and y-down.
+        for ring in self.iter() {
+            let cx = ring.origin.x + w / 2.0;
+            let cy = h / 2.0 - ring.origin.y;
+            svg.push_str(&format!(
+                "  <circle cx=\"{cx}\" cy=\"{cy}\" r=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>\n",
+                ring.radius,
+                rgb_to_hex(&ring.color),
+                ring.weight
+            ));
+        }
+
+        svg.push_str("</svg>\n");
+        std::fs::write(path, svg).expect("failed to write svg");
+    }
+}
+
+fn rgb_to_hex(c: &Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", c.red, c.green, c.blue)
+}
+
 impl PartialEq for Ring {
     fn eq(&self, other: &Self) -> bool {
         self.origin == other.origin && self.radius == other.radius && self.weight == other.weight && self.growth_rate == other.growth_rate && self.direction == other.direction
@@ -159,6 +360,13 @@ struct Model {
     current_bg: usize,
     rings: Vec<Ring>,
     button_state: nannou::state::mouse::ButtonPosition,
+    paused: bool,
+    speed_multiplier: f32,
+    behavior: Box<dyn RingBehavior>,
+    dla_enabled: bool,
+    walkers: Vec<Point>,
+    clustered: Vec<Point>,
+    arc_mode: bool,
 }
 
 impl Default for Model {
@@ -168,32 +376,196 @@ impl Default for Model {
             current_bg: usize::default(),
             rings: Vec::default(),
             button_state: nannou::state::mouse::ButtonPosition::Up,
+            paused: false,
+            speed_multiplier: 1.0,
+            behavior: Box::new(DefaultBehavior),
+            dla_enabled: false,
+            walkers: Vec::default(),
+            clustered: Vec::default(),
+            arc_mode: false,
         }
     }
 }
 
-impl Nannou for Model {
+impl Model {
     fn display(&self, draw: &Draw) {
         draw.background().color(Rgb::from(self.bg_color));
-        self.rings.display(draw);
+        self.rings.display(draw, self.arc_mode);
     }
+
     /// Update this model
     fn update(&mut self) {
-        self.rings.update();
+        if self.dla_enabled {
+            self.step_dla();
+        }
+        self.rings.update(self.behavior.as_mut());
+    }
+
+    fn save_svg(&self, path: &Path, win_rect: Rect) {
+        self.rings.save_svg(path, win_rect, Rgb::from(self.bg_color));
+    }
+
+    fn handle_command(&mut self, command: Command, win_rect: Rect) {
+        match command {
+            Command::Clear => self.rings.clear(),
+            Command::Pause => self.paused = !self.paused,
+            Command::Step => {
+                if self.paused {
+                    self.update();
+                }
+            }
+            Command::UndoLastRing => {
+                self.rings.pop();
+            }
+            Command::CycleBackground => {
+                self.current_bg = (self.current_bg + 1) % Color::ALL.len();
+                self.bg_color = Color::ALL[self.current_bg];
+            }
+            Command::SpeedUp => self.scale_speed(1.25),
+            Command::SlowDown => self.scale_speed(0.8),
+            Command::ToggleDla => {
+                self.dla_enabled = !self.dla_enabled;
+                if self.dla_enabled {
+                    self.seed_dla(win_rect);
+                }
+            }
+            Command::ToggleArcDisplay => self.arc_mode = !self.arc_mode,
+        }
+    }
+
+    fn scale_speed(&mut self, factor: f32) {
+        self.speed_multiplier *= factor;
+        for ring in self.rings.iter_mut() {
+            ring.growth_rate *= factor;
+        }
+    }
+
+    /// Seeds diffusion-limited aggregation with a cluster of one point at the
+    /// origin and a field of free walkers scattered across the window.
+    fn seed_dla(&mut self, win_rect: Rect) {
+        const WALKER_COUNT: usize = 200;
+        let mut rng = rand::thread_rng();
+        self.clustered = vec![Point::default()];
+        self.walkers = (0..WALKER_COUNT)
+            .map(|_| {
+                Point::new(
+                    rng.gen_range(win_rect.left()..win_rect.right()),
+                    rng.gen_range(win_rect.bottom()..win_rect.top()),
+                )
+            })
+            .collect();
+    }
+
+    /// Advances diffusion-limited aggregation by one frame: jitters every
+    /// walker, then sticks any that have drifted within capture range of the
+    /// clustered set and spawns a ring at each new crystallization site.
+    fn step_dla(&mut self) {
+        const STEP: f32 = 2.0;
+        const RAD: f32 = 4.0;
+
+        let mut rng = rand::thread_rng();
+        for walker in self.walkers.iter_mut() {
+            walker.x += rng.gen_range(-STEP..STEP);
+            walker.y += rng.gen_range(-STEP..STEP);
+        }
+
+        self.clustered.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
+
+        let mut stuck = Vec::new();
+        for (i, walker) in self.walkers.iter().enumerate() {
+            if clustered_neighbor_within(&self.clustered, walker, RAD) {
+                stuck.push(i);
+            }
+        }
+
+        for &i in stuck.iter().rev() {
+            let point = self.walkers.remove(i);
+            self.clustered.push(point);
+
+            let mut ring = Ring::new();
+            ring.set_origin(point.x, point.y);
+            ring.growth_rate *= self.speed_multiplier;
+            self.rings.push(ring);
+        }
     }
 }
 
+/// True if `clustered` (sorted by `x`) holds a point within `rad` of `p`.
+fn clustered_neighbor_within(clustered: &[Point], p: &Point, rad: f32) -> bool {
+    let rad_sq = rad * rad;
+    for c in clustered {
+        if c.x > p.x + rad {
+            break;
+        }
+        if c.x < p.x - rad {
+            continue;
+        }
+        let dx = c.x - p.x;
+        let dy = c.y - p.y;
+        if dx * dx + dy * dy < rad_sq {
+            return true;
+        }
+    }
+    false
+}
 
-impl Nannou for Vec<Ring> {
-    fn display(&self, draw: &Draw) {
+
+impl Render for Vec<Ring> {
+    fn display(&self, draw: &Draw, arc_mode: bool) {
         for ring in self.iter() {
-            ring.display(draw);
+            ring.display(draw, self, arc_mode);
         }
     }
-    fn update(&mut self) {
+}
+
+impl Simulate for Vec<Ring> {
+    fn update(&mut self, behavior: &mut dyn RingBehavior) {
         let clone = self.clone();
-        for ring in self.iter_mut() {
-            ring.update(&clone);
+        let max_radius = clone.iter().map(|r| r.radius).fold(0.0_f32, f32::max);
+
+        // Sweep and prune: visit rings in x-sorted order so the inner search
+        // only has to walk outward until it's out of reach, turning the
+        // O(n^2) all-pairs scan into roughly O(n log n).
+        let mut order: Vec<usize> = (0..clone.len()).collect();
+        order.sort_by(|&a, &b| clone[a].origin.x.partial_cmp(&clone[b].origin.x).unwrap());
+        let mut position = vec![0usize; clone.len()];
+        for (pos, &idx) in order.iter().enumerate() {
+            position[idx] = pos;
+        }
+
+        for (idx, ring) in self.iter_mut().enumerate() {
+            let max_reach = max_radius + ring.radius + ring.growth_rate;
+            let pos = position[idx];
+            let mut intersecting = false;
+
+            for &other_idx in &order[pos + 1..] {
+                let other = &clone[other_idx];
+                if other.origin.x > ring.origin.x + max_reach {
+                    break;
+                }
+                if ring.is_intersecting(other) {
+                    intersecting = true;
+                    break;
+                }
+            }
+
+            if !intersecting {
+                for &other_idx in order[..pos].iter().rev() {
+                    let other = &clone[other_idx];
+                    if other.origin.x < ring.origin.x - max_reach {
+                        break;
+                    }
+                    if ring.is_intersecting(other) {
+                        intersecting = true;
+                        break;
+                    }
+                }
+            }
+
+            let (radius, direction) =
+                behavior.update_ring(ring.radius, ring.growth_rate, ring.direction.to_i32(), intersecting);
+            ring.radius = radius;
+            ring.direction = RingDirection::from_i32(direction);
         }
     }
 }
@@ -207,7 +579,16 @@ impl Nannou for Vec<Ring> {
 
 /// Nannou app model
 fn model(_app: &App) -> Model {
-    Model::default()
+    let mut model = Model::default();
+
+    if let Some(script_path) = std::env::args().nth(1) {
+        match wasm_host::WasmBehavior::load(Path::new(&script_path)) {
+            Ok(behavior) => model.behavior = Box::new(behavior),
+            Err(err) => eprintln!("failed to load ring behavior '{}': {}", script_path, err),
+        }
+    }
+
+    model
 }
 
 /// Nannou app updates
@@ -224,14 +605,37 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
             nannou::state::mouse::ButtonPosition::Down(pos) => {
                 let mut new_ring = Ring::new();
                 new_ring.set_origin(pos.x, pos.y);
+                new_ring.growth_rate *= model.speed_multiplier;
                 model.rings.push(new_ring);
             },
         }
         model.button_state = *left_button;
     }
-    model.update();
+    if !model.paused {
+        model.update();
+    }
+}
+
+/// Nannou app events
+fn event(app: &App, model: &mut Model, event: Event) {
+    if let Event::WindowEvent {
+        simple: Some(WindowEvent::KeyPressed(key)),
+        ..
+    } = event
+    {
+        key_pressed(app, model, key);
+    }
+}
 
-    
+/// Handles a single key press
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if key == Key::S {
+        model.save_svg(Path::new("rings.svg"), app.window_rect());
+        return;
+    }
+    if let Some(command) = Command::from_key(key) {
+        model.handle_command(command, app.window_rect());
+    }
 }
 
 /// Nannou app view
@@ -0,0 +1,53 @@
+//! Host side of the WASM ring-behavior ABI.
+//!
+//! A script replaces `DefaultBehavior`'s fixed grow/shrink-on-intersect rule
+//! with one exported WASM function, `update_ring`, called once per ring per
+//! frame with `(radius: f32, growth_rate: f32, direction: i32, intersecting: i32)`
+//! and returning `(radius: f32, direction: i32)`. The returned radius is the
+//! ring's new ABSOLUTE radius, not a delta. `scripts/default_behavior.wat`
+//! ships as a template replicating `DefaultBehavior`.
+
+use crate::RingBehavior;
+use std::path::Path;
+use wasmtime::{Engine, Instance, Module, Store, TypedFunc};
+
+pub struct WasmBehavior {
+    store: Store<()>,
+    update_ring: TypedFunc<(f32, f32, i32, i32), (f32, i32)>,
+    disabled: bool,
+}
+
+impl WasmBehavior {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let engine = Engine::default();
+        let module = Module::from_file(&engine, path)?;
+        let mut store = Store::new(&engine, ());
+        let instance = Instance::new(&mut store, &module, &[])?;
+        let update_ring = instance.get_typed_func(&mut store, "update_ring")?;
+        Ok(Self {
+            store,
+            update_ring,
+            disabled: false,
+        })
+    }
+}
+
+impl RingBehavior for WasmBehavior {
+    fn update_ring(&mut self, radius: f32, growth_rate: f32, direction: i32, intersecting: bool) -> (f32, i32) {
+        if self.disabled {
+            return (radius, direction);
+        }
+
+        match self
+            .update_ring
+            .call(&mut self.store, (radius, growth_rate, direction, intersecting as i32))
+        {
+            Ok(result) => result,
+            Err(err) => {
+                eprintln!("ring behavior script trapped in update_ring, disabling it: {}", err);
+                self.disabled = true;
+                (radius, direction)
+            }
+        }
+    }
+}